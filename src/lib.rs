@@ -1,16 +1,23 @@
 #![doc = include_str!("../README.md")]
 use pin_project_lite::pin_project;
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
     hash::Hash,
+    pin::Pin,
     task::Poll,
+    time::Duration,
 };
+use tokio::time::{Instant, Sleep};
 use tokio_stream::Stream;
 
 #[derive(Debug, PartialEq, Eq, Hash)]
-pub enum Action<T> {
+pub enum Action<T, K = T> {
     Add(T),
     Remove(T),
+    /// A key present both before and after kept its identity but changed
+    /// payload, as produced by [`StreamDiffExt::diff_by_key`].
+    Update { key: K, old: T, new: T },
 }
 
 #[derive(Debug)]
@@ -31,6 +38,10 @@ impl<T: Hash + Eq + Clone> Inner<T> {
         self.action_queue.pop_front()
     }
 
+    fn drain_queue(&mut self) -> Vec<Action<T>> {
+        self.action_queue.drain(..).collect()
+    }
+
     fn update<I>(&mut self, new_state: I)
     where
         I: IntoIterator<Item = T>,
@@ -116,6 +127,17 @@ where
     T: Hash + Eq + Sized + Clone,
 {
     fn diff(self) -> StreamDiff<Self, I, T>;
+
+    fn diff_debounced(self, duration: Duration) -> DebouncedStreamDiff<Self, I, T>;
+
+    fn diff_by_key<K, F>(self, key_fn: F) -> KeyedStreamDiff<Self, I, T, K, F>
+    where
+        F: Fn(&T) -> K,
+        K: Hash + Eq + Clone;
+
+    fn diff_with_idle(self, duration: Duration) -> IdleStreamDiff<Self, I, T>;
+
+    fn diff_batched(self) -> BatchedStreamDiff<Self, I, T>;
 }
 
 impl<S, I, T> StreamDiffExt<I, T> for S
@@ -127,6 +149,710 @@ where
     fn diff(self) -> StreamDiff<Self, I, T> {
         StreamDiff::new(self)
     }
+
+    fn diff_debounced(self, duration: Duration) -> DebouncedStreamDiff<Self, I, T> {
+        DebouncedStreamDiff::new(self, duration)
+    }
+
+    fn diff_by_key<K, F>(self, key_fn: F) -> KeyedStreamDiff<Self, I, T, K, F>
+    where
+        F: Fn(&T) -> K,
+        K: Hash + Eq + Clone,
+    {
+        KeyedStreamDiff::new(self, key_fn)
+    }
+
+    fn diff_with_idle(self, duration: Duration) -> IdleStreamDiff<Self, I, T> {
+        IdleStreamDiff::new(self, duration)
+    }
+
+    fn diff_batched(self) -> BatchedStreamDiff<Self, I, T> {
+        BatchedStreamDiff::new(self)
+    }
+}
+
+pin_project! {
+    /// Like [`StreamDiff`], but coalesces a burst of snapshots arriving within
+    /// `duration` of each other into a single diff against the last snapshot
+    /// before the burst, so intermediate states that immediately cancel out
+    /// never reach the consumer.
+    pub struct DebouncedStreamDiff<S, I, T>
+    where
+        S: Stream<Item = I>,
+        I: IntoIterator<Item = T>,
+    {
+        #[pin]
+        stream: S,
+        // Boxed and owned-pinned rather than `#[pin]`, so this type stays
+        // `Unpin` whenever `S` is (matching every other adapter in the
+        // crate) instead of being unconditionally `!Unpin` because of
+        // `Sleep`'s internal `PhantomPinned`.
+        sleep: std::pin::Pin<Box<Sleep>>,
+        duration: Duration,
+        pending: Option<I>,
+        inner: Inner<T>,
+    }
+}
+
+impl<S, I, T> DebouncedStreamDiff<S, I, T>
+where
+    S: Stream<Item = I>,
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq + Sized + Clone,
+{
+    pub fn new(stream: S, duration: Duration) -> Self {
+        Self {
+            stream,
+            sleep: Box::pin(tokio::time::sleep(duration)),
+            duration,
+            pending: None,
+            inner: Inner::new(),
+        }
+    }
+}
+
+impl<S, I, T> Stream for DebouncedStreamDiff<S, I, T>
+where
+    S: Stream<Item = I>,
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq + Sized + Clone,
+{
+    type Item = Action<T>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            let mut me = self.as_mut().project();
+
+            if let Some(action) = me.inner.next_from_queue() {
+                return Poll::Ready(Some(action));
+            }
+
+            match me.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    *me.pending = Some(item);
+                    me.sleep.as_mut().reset(Instant::now() + *me.duration);
+                    continue;
+                }
+                Poll::Ready(None) => {
+                    return match me.pending.take() {
+                        Some(item) => {
+                            me.inner.update(item);
+                            continue;
+                        }
+                        None => Poll::Ready(None),
+                    };
+                }
+                Poll::Pending => {}
+            }
+
+            match me.sleep.as_mut().poll(cx) {
+                Poll::Ready(()) => {
+                    // Re-arm regardless of whether a snapshot was pending, so a
+                    // fired timer with nothing to flush simply re-parks instead
+                    // of spinning.
+                    me.sleep.as_mut().reset(Instant::now() + *me.duration);
+                    match me.pending.take() {
+                        Some(item) => {
+                            me.inner.update(item);
+                            continue;
+                        }
+                        None => return Poll::Pending,
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MergeInner<T> {
+    refcounts: HashMap<T, usize>,
+    action_queue: VecDeque<Action<T>>,
+}
+
+impl<T: Hash + Eq + Clone> MergeInner<T> {
+    fn new() -> Self {
+        Self {
+            refcounts: HashMap::new(),
+            action_queue: Default::default(),
+        }
+    }
+
+    fn next_from_queue(&mut self) -> Option<Action<T>> {
+        self.action_queue.pop_front()
+    }
+
+    /// Accounts for one source going from `old` to `new`, emitting an
+    /// `Action::Add` only on a 0->1 refcount transition and an
+    /// `Action::Remove` only on a 1->0 transition.
+    fn update_source(&mut self, old: &HashSet<T>, new: &HashSet<T>) {
+        for added in new.difference(old) {
+            let count = self.refcounts.entry(added.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                self.action_queue.push_back(Action::Add(added.clone()));
+            }
+        }
+        for removed in old.difference(new) {
+            if let Some(count) = self.refcounts.get_mut(removed) {
+                *count -= 1;
+                if *count == 0 {
+                    self.refcounts.remove(removed);
+                    self.action_queue.push_back(Action::Remove(removed.clone()));
+                }
+            }
+        }
+    }
+}
+
+/// Diffs the union of several state-source streams, treating an item as
+/// present as long as at least one source has reported it. A source's
+/// contribution persists at its last reported snapshot once that source's
+/// stream ends rather than being retracted to empty, since ending isn't the
+/// same as reporting "I no longer have any items".
+///
+/// Sources are polled round-robin (tracked via `next_index`) so that one
+/// chatty source can't starve the others out of a single `poll_next` call.
+pub struct StreamDiffMerge<S, T>
+where
+    S: Stream,
+    S::Item: IntoIterator<Item = T>,
+{
+    streams: Vec<Option<Pin<Box<S>>>>,
+    last_state: Vec<HashSet<T>>,
+    inner: MergeInner<T>,
+    next_index: usize,
+}
+
+// Nothing here is structurally pinned: each source is already pinned
+// independently via `Pin<Box<S>>`, so moving `StreamDiffMerge` itself can't
+// move a pinned source out from under anyone.
+impl<S, T> Unpin for StreamDiffMerge<S, T>
+where
+    S: Stream,
+    S::Item: IntoIterator<Item = T>,
+{
+}
+
+impl<S, T> StreamDiffMerge<S, T>
+where
+    S: Stream,
+    S::Item: IntoIterator<Item = T>,
+    T: Hash + Eq + Sized + Clone,
+{
+    pub fn new(streams: Vec<S>) -> Self {
+        let last_state = streams.iter().map(|_| HashSet::new()).collect();
+        Self {
+            streams: streams.into_iter().map(|s| Some(Box::pin(s))).collect(),
+            last_state,
+            inner: MergeInner::new(),
+            next_index: 0,
+        }
+    }
+}
+
+/// Free-function form of [`StreamDiffMerge::new`].
+pub fn diff_merge<S, T>(streams: Vec<S>) -> StreamDiffMerge<S, T>
+where
+    S: Stream,
+    S::Item: IntoIterator<Item = T>,
+    T: Hash + Eq + Sized + Clone,
+{
+    StreamDiffMerge::new(streams)
+}
+
+impl<S, T> Stream for StreamDiffMerge<S, T>
+where
+    S: Stream,
+    S::Item: IntoIterator<Item = T>,
+    T: Hash + Eq + Sized + Clone,
+{
+    type Item = Action<T>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let me = self.get_mut();
+        loop {
+            if let Some(action) = me.inner.next_from_queue() {
+                return Poll::Ready(Some(action));
+            }
+
+            let len = me.streams.len();
+            if len == 0 || me.streams.iter().all(Option::is_none) {
+                return Poll::Ready(None);
+            }
+
+            let mut progressed = false;
+            for step in 0..len {
+                let idx = (me.next_index + step) % len;
+                let Some(stream) = me.streams[idx].as_mut() else {
+                    continue;
+                };
+
+                match stream.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(item)) => {
+                        let new_state = HashSet::from_iter(item);
+                        me.inner.update_source(&me.last_state[idx], &new_state);
+                        me.last_state[idx] = new_state;
+                        me.next_index = (idx + 1) % len;
+                        progressed = true;
+                        break;
+                    }
+                    Poll::Ready(None) => {
+                        // A finished source's last reported membership
+                        // persists rather than retracting to empty: the
+                        // source simply stops reporting changes, it isn't
+                        // announcing that all its items vanished.
+                        me.streams[idx] = None;
+                        me.next_index = (idx + 1) % len;
+                        progressed = true;
+                        break;
+                    }
+                    Poll::Pending => continue,
+                }
+            }
+
+            if !progressed {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct KeyedInner<K, T> {
+    state: HashMap<K, T>,
+    action_queue: VecDeque<Action<T, K>>,
+}
+
+impl<K: Hash + Eq + Clone, T: PartialEq + Clone> KeyedInner<K, T> {
+    fn new() -> Self {
+        Self {
+            state: HashMap::new(),
+            action_queue: Default::default(),
+        }
+    }
+
+    fn next_from_queue(&mut self) -> Option<Action<T, K>> {
+        self.action_queue.pop_front()
+    }
+
+    fn update<I, F>(&mut self, new_state: I, key_fn: &F)
+    where
+        I: IntoIterator<Item = T>,
+        F: Fn(&T) -> K,
+    {
+        let new_state: HashMap<K, T> = new_state
+            .into_iter()
+            .map(|value| (key_fn(&value), value))
+            .collect();
+
+        let mut action_queue = VecDeque::new();
+        for (key, old_value) in &self.state {
+            match new_state.get(key) {
+                None => action_queue.push_back(Action::Remove(old_value.clone())),
+                Some(new_value) if new_value != old_value => {
+                    action_queue.push_back(Action::Update {
+                        key: key.clone(),
+                        old: old_value.clone(),
+                        new: new_value.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, new_value) in &new_state {
+            if !self.state.contains_key(key) {
+                action_queue.push_back(Action::Add(new_value.clone()));
+            }
+        }
+
+        self.action_queue = action_queue;
+        self.state = new_state;
+    }
+}
+
+pin_project! {
+    /// Like [`StreamDiff`], but identifies items by a stable key extracted via
+    /// `key_fn` instead of full-value equality, so a record whose payload
+    /// changes while its key stays the same produces an [`Action::Update`]
+    /// rather than a `Remove` followed by an `Add`.
+    pub struct KeyedStreamDiff<S, I, T, K, F>
+    where
+        S: Stream<Item = I>,
+        I: IntoIterator<Item = T>,
+        F: Fn(&T) -> K,
+    {
+        #[pin]
+        stream: S,
+        key_fn: F,
+        inner: KeyedInner<K, T>,
+    }
+}
+
+impl<S, I, T, K, F> KeyedStreamDiff<S, I, T, K, F>
+where
+    S: Stream<Item = I>,
+    I: IntoIterator<Item = T>,
+    T: PartialEq + Clone,
+    K: Hash + Eq + Clone,
+    F: Fn(&T) -> K,
+{
+    pub fn new(stream: S, key_fn: F) -> Self {
+        Self {
+            stream,
+            key_fn,
+            inner: KeyedInner::new(),
+        }
+    }
+}
+
+impl<S, I, T, K, F> Stream for KeyedStreamDiff<S, I, T, K, F>
+where
+    S: Stream<Item = I>,
+    I: IntoIterator<Item = T>,
+    T: PartialEq + Clone,
+    K: Hash + Eq + Clone,
+    F: Fn(&T) -> K,
+{
+    type Item = Action<T, K>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            let me = self.as_mut().project();
+
+            if let Some(action) = me.inner.next_from_queue() {
+                return Poll::Ready(Some(action));
+            }
+
+            let state = match me.stream.poll_next(cx) {
+                Poll::Ready(Some(item)) => item,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            me.inner.update(state, me.key_fn);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+fn apply_action<T: Hash + Eq>(state: &mut HashSet<T>, action: Action<T>) {
+    match action {
+        Action::Add(item) => {
+            state.insert(item);
+        }
+        Action::Remove(item) => {
+            state.remove(&item);
+        }
+        Action::Update { old, new, .. } => {
+            state.remove(&old);
+            state.insert(new);
+        }
+    }
+}
+
+pin_project! {
+    /// The inverse of [`StreamDiff`]: consumes a stream of [`Action`]s and
+    /// yields the materialized `HashSet<T>` snapshot after applying each one,
+    /// so a `StreamDiff` output can be serialized, transmitted, and rehydrated
+    /// on the other side.
+    pub struct StreamUndiff<S, T>
+    where
+        S: Stream<Item = Action<T>>,
+    {
+        #[pin]
+        stream: S,
+        state: HashSet<T>,
+    }
+}
+
+impl<S, T> StreamUndiff<S, T>
+where
+    S: Stream<Item = Action<T>>,
+    T: Hash + Eq + Clone,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            state: HashSet::new(),
+        }
+    }
+}
+
+impl<S, T> Stream for StreamUndiff<S, T>
+where
+    S: Stream<Item = Action<T>>,
+    T: Hash + Eq + Clone,
+{
+    type Item = HashSet<T>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let me = self.project();
+        match me.stream.poll_next(cx) {
+            Poll::Ready(Some(action)) => {
+                apply_action(me.state, action);
+                Poll::Ready(Some(me.state.clone()))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+pub trait StreamUndiffExt<T>
+where
+    Self: Stream<Item = Action<T>> + Sized,
+    T: Hash + Eq + Clone,
+{
+    fn undiff(self) -> StreamUndiff<Self, T>;
+}
+
+impl<S, T> StreamUndiffExt<T> for S
+where
+    S: Stream<Item = Action<T>> + Sized,
+    T: Hash + Eq + Clone,
+{
+    fn undiff(self) -> StreamUndiff<Self, T> {
+        StreamUndiff::new(self)
+    }
+}
+
+pin_project! {
+    /// Batched sub-mode of [`StreamUndiff`]: consumes a stream of `Vec<Action<T>>`
+    /// batches (e.g. from [`StreamDiffExt::diff_batched`]) and emits one
+    /// snapshot per batch, only at those consumer-controlled flush points
+    /// instead of after every single action.
+    pub struct BatchedStreamUndiff<S, T>
+    where
+        S: Stream<Item = Vec<Action<T>>>,
+    {
+        #[pin]
+        stream: S,
+        state: HashSet<T>,
+    }
+}
+
+impl<S, T> BatchedStreamUndiff<S, T>
+where
+    S: Stream<Item = Vec<Action<T>>>,
+    T: Hash + Eq + Clone,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            state: HashSet::new(),
+        }
+    }
+}
+
+impl<S, T> Stream for BatchedStreamUndiff<S, T>
+where
+    S: Stream<Item = Vec<Action<T>>>,
+    T: Hash + Eq + Clone,
+{
+    type Item = HashSet<T>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let me = self.project();
+        match me.stream.poll_next(cx) {
+            Poll::Ready(Some(batch)) => {
+                for action in batch {
+                    apply_action(me.state, action);
+                }
+                Poll::Ready(Some(me.state.clone()))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+pub trait BatchedStreamUndiffExt<T>
+where
+    Self: Stream<Item = Vec<Action<T>>> + Sized,
+    T: Hash + Eq + Clone,
+{
+    fn undiff_batched(self) -> BatchedStreamUndiff<Self, T>;
+}
+
+impl<S, T> BatchedStreamUndiffExt<T> for S
+where
+    S: Stream<Item = Vec<Action<T>>> + Sized,
+    T: Hash + Eq + Clone,
+{
+    fn undiff_batched(self) -> BatchedStreamUndiff<Self, T> {
+        BatchedStreamUndiff::new(self)
+    }
+}
+
+/// Item yielded by [`IdleStreamDiff`]: either a real [`Action`] derived from a
+/// new snapshot, or an [`IdleAction::Idle`] marker emitted when the source has
+/// gone quiet for longer than the configured duration.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum IdleAction<T> {
+    Action(Action<T>),
+    Idle,
+}
+
+pin_project! {
+    /// Like [`StreamDiff`], but interleaves `Action`s with an `Idle` marker
+    /// whenever the source produces no new snapshot within `duration`, so
+    /// consumers can distinguish "membership is stable and fresh" from
+    /// "upstream may be stuck".
+    pub struct IdleStreamDiff<S, I, T>
+    where
+        S: Stream<Item = I>,
+        I: IntoIterator<Item = T>,
+    {
+        #[pin]
+        stream: S,
+        // See the identical field in `DebouncedStreamDiff`: boxed and
+        // owned-pinned rather than `#[pin]`, so this stays `Unpin` whenever
+        // `S` is, instead of being unconditionally `!Unpin`.
+        sleep: std::pin::Pin<Box<Sleep>>,
+        duration: Duration,
+        inner: Inner<T>,
+    }
+}
+
+impl<S, I, T> IdleStreamDiff<S, I, T>
+where
+    S: Stream<Item = I>,
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq + Sized + Clone,
+{
+    pub fn new(stream: S, duration: Duration) -> Self {
+        Self {
+            stream,
+            sleep: Box::pin(tokio::time::sleep(duration)),
+            duration,
+            inner: Inner::new(),
+        }
+    }
+}
+
+impl<S, I, T> Stream for IdleStreamDiff<S, I, T>
+where
+    S: Stream<Item = I>,
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq + Sized + Clone,
+{
+    type Item = IdleAction<T>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            let mut me = self.as_mut().project();
+
+            if let Some(action) = me.inner.next_from_queue() {
+                me.sleep.as_mut().reset(Instant::now() + *me.duration);
+                return Poll::Ready(Some(IdleAction::Action(action)));
+            }
+
+            match me.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    me.sleep.as_mut().reset(Instant::now() + *me.duration);
+                    me.inner.update(item);
+                    continue;
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => {}
+            }
+
+            match me.sleep.as_mut().poll(cx) {
+                Poll::Ready(()) => {
+                    me.sleep.as_mut().reset(Instant::now() + *me.duration);
+                    return Poll::Ready(Some(IdleAction::Idle));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+pin_project! {
+    /// Like [`StreamDiff`], but delivers every `Action` derived from one
+    /// upstream snapshot together as a single `Vec<Action<T>>`, instead of one
+    /// at a time across many `poll_next` calls. Snapshots that produce no
+    /// change are suppressed rather than yielding an empty `Vec`.
+    pub struct BatchedStreamDiff<S, I, T>
+    where
+        S: Stream<Item = I>,
+        I: IntoIterator<Item = T>,
+    {
+        #[pin]
+        stream: S,
+        inner: Inner<T>,
+    }
+}
+
+impl<S, I, T> BatchedStreamDiff<S, I, T>
+where
+    S: Stream<Item = I>,
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq + Sized + Clone,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            inner: Inner::new(),
+        }
+    }
+}
+
+impl<S, I, T> Stream for BatchedStreamDiff<S, I, T>
+where
+    S: Stream<Item = I>,
+    I: IntoIterator<Item = T>,
+    T: Hash + Eq + Sized + Clone,
+{
+    type Item = Vec<Action<T>>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            let me = self.as_mut().project();
+
+            let state = match me.stream.poll_next(cx) {
+                Poll::Ready(Some(item)) => item,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            me.inner.update(state);
+            let batch = me.inner.drain_queue();
+            if !batch.is_empty() {
+                return Poll::Ready(Some(batch));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
 }
 
 #[cfg(test)]
@@ -172,4 +898,152 @@ mod tests {
 
         assert_eq!(stream_diff.next().await, None);
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_debounced() {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut stream_diff =
+            tokio_stream::wrappers::UnboundedReceiverStream::new(rx).diff_debounced(Duration::from_millis(100));
+
+        tx.send(vec![1]).unwrap();
+        tx.send(vec![1, 2]).unwrap();
+        tx.send(vec![1]).unwrap();
+
+        tokio::time::advance(Duration::from_millis(150)).await;
+
+        assert_eq!(stream_diff.next().await, Some(Action::Add(1)));
+
+        drop(tx);
+        assert_eq!(stream_diff.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_diff_merge() {
+        // 2 is shared by both sources: it must not produce a Remove until
+        // *both* sources have stopped reporting it via a live update. 1 is
+        // only ever held by `a`, which ends without reporting it gone, so a
+        // finished source's last reported membership persists rather than
+        // retracting to empty.
+        let a = tokio_stream::iter([vec![1, 2], vec![1]]);
+        let b = tokio_stream::iter([vec![2], vec![]]);
+        let mut merged = diff_merge(vec![a, b]);
+
+        // Both Adds come from the same `a` snapshot and are pushed in
+        // HashSet iteration order, which isn't stable, so check them
+        // unordered.
+        let take_2 = [merged.next().await, merged.next().await];
+        assert!(take_2.contains(&Some(Action::Add(1))));
+        assert!(take_2.contains(&Some(Action::Add(2))));
+
+        assert_eq!(merged.next().await, Some(Action::Remove(2)));
+        assert_eq!(merged.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_diff_by_key() {
+        let states = [
+            vec![(1, "a")],
+            vec![(1, "b")],
+            vec![(1, "b"), (2, "c")],
+            vec![(2, "c")],
+        ];
+        let mut stream_diff = tokio_stream::iter(states).diff_by_key(|(key, _)| *key);
+
+        assert_eq!(
+            stream_diff.next().await,
+            Some(Action::Add((1, "a")))
+        );
+        assert_eq!(
+            stream_diff.next().await,
+            Some(Action::Update {
+                key: 1,
+                old: (1, "a"),
+                new: (1, "b")
+            })
+        );
+        assert_eq!(
+            stream_diff.next().await,
+            Some(Action::Add((2, "c")))
+        );
+        assert_eq!(
+            stream_diff.next().await,
+            Some(Action::Remove((1, "b")))
+        );
+        assert_eq!(stream_diff.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_undiff() {
+        let states = [vec![], vec![1], vec![1, 2], vec![2], vec![]];
+        let mut snapshots = tokio_stream::iter(states.clone()).diff().undiff();
+
+        let mut last = HashSet::new();
+        for expected in &states[1..] {
+            last = snapshots.next().await.unwrap();
+            assert_eq!(last, HashSet::from_iter(expected.iter().cloned()));
+        }
+        assert_eq!(snapshots.next().await, None);
+        assert_eq!(last, HashSet::new());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_diff_with_idle() {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut stream_diff = tokio_stream::wrappers::UnboundedReceiverStream::new(rx)
+            .diff_with_idle(Duration::from_millis(100));
+
+        tx.send(vec![1]).unwrap();
+        assert_eq!(
+            stream_diff.next().await,
+            Some(IdleAction::Action(Action::Add(1)))
+        );
+
+        tokio::time::advance(Duration::from_millis(150)).await;
+        assert_eq!(stream_diff.next().await, Some(IdleAction::Idle));
+
+        tx.send(vec![1, 2]).unwrap();
+        assert_eq!(
+            stream_diff.next().await,
+            Some(IdleAction::Action(Action::Add(2)))
+        );
+
+        drop(tx);
+        assert_eq!(stream_diff.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_diff_batched() {
+        let states = [vec![], vec![1, 2, 3], vec![1], vec![]];
+        let mut batches = tokio_stream::iter(states).diff_batched();
+
+        let first = batches.next().await.unwrap();
+        assert_eq!(first.len(), 3);
+        assert!(first.contains(&Action::Add(1)));
+        assert!(first.contains(&Action::Add(2)));
+        assert!(first.contains(&Action::Add(3)));
+
+        let second = batches.next().await.unwrap();
+        assert_eq!(second.len(), 2);
+        assert!(second.contains(&Action::Remove(2)));
+        assert!(second.contains(&Action::Remove(3)));
+
+        let third = batches.next().await.unwrap();
+        assert_eq!(third, vec![Action::Remove(1)]);
+
+        assert_eq!(batches.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_undiff_batched() {
+        let states = [vec![], vec![1, 2, 3], vec![1], vec![]];
+        let mut snapshots = tokio_stream::iter(states).diff_batched().undiff_batched();
+
+        assert_eq!(
+            snapshots.next().await,
+            Some(HashSet::from_iter([1, 2, 3]))
+        );
+        assert_eq!(snapshots.next().await, Some(HashSet::from_iter([1])));
+        assert_eq!(snapshots.next().await, Some(HashSet::new()));
+        assert_eq!(snapshots.next().await, None);
+    }
 }